@@ -0,0 +1,43 @@
+//! Persistent single-letter directory bookmarks, stored as `letter path`
+//! lines at `dirs::config_dir()/.rrr/bookmarks`.
+use crate::*;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+};
+
+fn file() -> Option<PathBuf> {
+    let mut path = dirs::config_dir()?;
+    path.push(".rrr");
+    path.push("bookmarks");
+    Some(path)
+}
+
+#[throws]
+pub fn load() -> HashMap<char, PathBuf> {
+    match file() {
+        Some(path) if path.exists() => fs::read_to_string(path)?
+            .lines()
+            .filter_map(|line| {
+                let (letter, directory) = line.split_once(' ')?;
+                Some((letter.chars().next()?, PathBuf::from(directory)))
+            })
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
+
+#[throws]
+pub fn save(bookmarks: &HashMap<char, PathBuf>) {
+    if let Some(path) = file() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(path)?;
+        for (letter, directory) in bookmarks {
+            writeln!(&mut file, "{} {}", letter, directory.display())?;
+        }
+    }
+}
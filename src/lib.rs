@@ -1,3 +1,5 @@
+pub mod bookmarks;
+pub mod operations;
 pub mod state;
 pub mod style;
 
@@ -2,6 +2,9 @@ use rrr::{state::Mode, *};
 use std::{
     fs::File,
     io::{prelude::*, stdin, stdout},
+    sync::mpsc::{channel, RecvTimeoutError, Sender},
+    thread,
+    time::Duration,
 };
 use termion::{event::Key, input::TermRead, raw::IntoRawMode, screen::AlternateScreen};
 use tui::{
@@ -13,6 +16,26 @@ use tui::{
     Terminal,
 };
 
+/// Feeds the main loop from a background thread so it can poll with a
+/// timeout instead of blocking on `stdin`, leaving room to also drain
+/// filesystem watch events while idle.
+enum Event {
+    Key(Key),
+}
+
+/// Blocks for exactly one keypress, sends it, then exits. Spawned again
+/// after each key is fully handled (not before) so no thread is ever
+/// blocked reading `stdin` while a child process like `$EDITOR` or
+/// `open` has it inherited — otherwise the background reader steals the
+/// keystrokes meant for the child.
+fn spawn_reader(sender: Sender<Event>) {
+    thread::spawn(move || {
+        if let Some(Ok(key)) = stdin().keys().next() {
+            let _ = sender.send(Event::Key(key));
+        }
+    });
+}
+
 #[throws]
 fn main() {
     let stdout = stdout().into_raw_mode()?;
@@ -21,18 +44,26 @@ fn main() {
     let mut terminal = Terminal::new(backend)?;
     let mut views = state::Views::new()?;
 
+    let (sender, receiver) = channel();
+    spawn_reader(sender.clone());
+
     'update: loop {
         // Assign current context, immutable moves here
         let mode = views.mode;
         let index = views.index + 1;
-        let command = if views.mode == Mode::Command {
-            format!(":{}", views.command)
-        } else {
-            String::new()
+        let command = match views.mode {
+            Mode::Command => format!(":{}", views.command),
+            Mode::Search => format!("/{}", views.contexts[views.index].query),
+            Mode::Normal => views.message.clone(),
         };
+        let pending = views.pending;
+        let show_preview = views.show_preview;
+        let syntax_set = views.syntax_set.clone();
+        let theme_set = views.theme_set.clone();
 
         // Mutable borrows start here
         let mut context = views.current_context();
+        context.process_watch_events();
         context.clamp_cursor()?;
 
         // Assign terminal size for paging
@@ -40,6 +71,11 @@ fn main() {
 
         // Create listing of files
         let listing = context.listing()?;
+        let preview = if show_preview {
+            Some(context.preview(&syntax_set, &theme_set))
+        } else {
+            None
+        };
 
         terminal.draw(|frame| {
             let size = frame.size();
@@ -71,13 +107,57 @@ fn main() {
             let command = Paragraph::new(command);
 
             // Render
-            frame.render_widget(files, chunks[0]);
+            match preview {
+                Some(preview) => {
+                    let panes = Layout::default()
+                        .direction(tui::layout::Direction::Horizontal)
+                        .constraints(
+                            [Constraint::Percentage(50), Constraint::Percentage(50)].as_ref(),
+                        )
+                        .split(chunks[0]);
+                    let preview = Paragraph::new(preview).block(Block::default());
+                    frame.render_widget(files, panes[0]);
+                    frame.render_widget(preview, panes[1]);
+                }
+                None => frame.render_widget(files, chunks[0]),
+            }
             frame.render_widget(command, chunks[1]);
         })?;
 
-        for key in stdin().keys() {
-            if let Ok(key) = key {
+        match receiver.recv_timeout(Duration::from_millis(200)) {
+            Ok(Event::Key(key)) => {
                 match mode {
+                    // Note: this arm must not touch `context` (borrowed from
+                    // `views` above) and `views.*` together, so it reaches
+                    // into `views.contexts[views.index]` directly instead.
+                    Mode::Normal if pending.is_some() => {
+                        let marker = views.pending.take().unwrap();
+                        if let Key::Char(letter) = key {
+                            match marker {
+                                'm' => {
+                                    let index = views.index;
+                                    let current_dir = views.contexts[index].current_dir.clone();
+                                    views.bookmarks.insert(letter, current_dir);
+                                }
+                                '\'' => {
+                                    if let Some(target) = views.bookmarks.get(&letter).cloned() {
+                                        let context = &mut views.contexts[views.index];
+                                        context.save_buffer();
+                                        let backup = context.current_dir.clone();
+                                        context.current_dir = target;
+                                        if context.read_directory().is_err() {
+                                            context.current_dir = backup;
+                                        } else {
+                                            context.query = String::new();
+                                        }
+                                        if context.watch().is_err() {}
+                                        context.restore_buffer();
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
                     Mode::Normal => match key {
                         Key::Char('q') | Key::Ctrl('c') | Key::Ctrl('z') => break 'update,
                         Key::Up | Key::Char('k') => {
@@ -92,17 +172,23 @@ fn main() {
                             context.current_dir.pop();
                             if context.read_directory().is_err() {
                                 context.current_dir = backup;
+                            } else {
+                                context.query = String::new();
                             }
+                            if context.watch().is_err() {}
                             context.restore_buffer();
                         }
                         Key::Right | Key::Char('l') => {
                             if let Ok(target) = context.target_dir() {
                                 context.save_buffer();
                                 let backup = context.current_dir.clone();
-                                context.current_dir.push(target);
+                                context.current_dir = target;
                                 if context.read_directory().is_err() {
                                     context.current_dir = backup;
+                                } else {
+                                    context.query = String::new();
                                 }
+                                if context.watch().is_err() {}
                                 context.restore_buffer();
                             }
                         }
@@ -147,6 +233,26 @@ fn main() {
                             }
                         }
                         Key::Char(':') => views.mode = Mode::Command,
+                        Key::Char('p') => views.toggle_preview(),
+                        Key::Char('z') => {
+                            if let Some(entry) = context.target() {
+                                let path = entry.path();
+                                if path.is_dir() {
+                                    if let Some(buffer) = context.buffer_mut() {
+                                        if !buffer.expanded.remove(&path) {
+                                            buffer.expanded.insert(path);
+                                        }
+                                    }
+                                    context.read_directory()?;
+                                }
+                            }
+                        }
+                        Key::Char('m') => views.pending = Some('m'),
+                        Key::Char('\'') => views.pending = Some('\''),
+                        Key::Char('/') => {
+                            context.query = String::new();
+                            views.mode = Mode::Search;
+                        }
                         Key::Char(' ') => {
                             if let Some(entry) = context.target() {
                                 let path = entry.path();
@@ -160,7 +266,7 @@ fn main() {
                     },
                     Mode::Command => match key {
                         Key::Char('\n') => {
-                            views.execute_command()?;
+                            views.execute_command();
                             views.exit_command();
                             terminal.clear()?;
                         },
@@ -172,15 +278,41 @@ fn main() {
                         }
                         _ => {}
                     },
+                    Mode::Search => match key {
+                        Key::Char('\n') => {
+                            context.clamp_cursor()?;
+                            views.mode = Mode::Normal;
+                        }
+                        Key::Esc => {
+                            context.query = String::new();
+                            views.mode = Mode::Normal;
+                        }
+                        Key::Char(c) => {
+                            context.query.push(c);
+                            context.cursor = 0;
+                            context.scroll = 0;
+                        }
+                        Key::Backspace => {
+                            context.query.pop();
+                            context.cursor = 0;
+                            context.scroll = 0;
+                        }
+                        _ => {}
+                    },
                 }
-                continue 'update;
+                spawn_reader(sender.clone());
             }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break 'update,
         }
     }
 
     // Fix wonkyness
     terminal.clear()?;
 
+    // Persist bookmarks
+    if bookmarks::save(&views.bookmarks).is_err() {}
+
     // Write last entered directory into temporary file
     if let Some(mut path) = dirs::cache_dir() {
         path.push(".rrr");
@@ -0,0 +1,71 @@
+//! File operations acting on marked entries (or the cursor target when
+//! nothing is marked): copy, move, trash, rename, mkdir and touch.
+use crate::*;
+use std::{
+    fs::{self, OpenOptions},
+    path::{Path, PathBuf},
+};
+
+#[throws]
+pub fn copy(paths: &[PathBuf], destination: &Path) {
+    for path in paths {
+        let target = destination.join(path.file_name().unwrap_or_default());
+        if path == &target {
+            continue;
+        }
+        if path.is_dir() {
+            copy_dir(path, &target)?;
+        } else {
+            fs::copy(path, target)?;
+        }
+    }
+}
+
+#[throws]
+fn copy_dir(source: &Path, destination: &Path) {
+    fs::create_dir_all(destination)?;
+    for entry in fs::read_dir(source)?.flatten() {
+        let target = destination.join(entry.file_name());
+        if entry.path().is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+}
+
+#[throws]
+pub fn r#move(paths: &[PathBuf], destination: &Path) {
+    for path in paths {
+        let target = destination.join(path.file_name().unwrap_or_default());
+        fs::rename(path, target)?;
+    }
+}
+
+#[throws]
+pub fn trash(paths: &[PathBuf]) {
+    for path in paths {
+        trash::delete(path)?;
+    }
+}
+
+#[throws]
+pub fn rename(path: &Path, name: &str) {
+    fs::rename(path, path.with_file_name(name))?;
+}
+
+#[throws]
+pub fn mkdir(destination: &Path, name: &str) {
+    fs::create_dir(destination.join(name))?;
+}
+
+#[throws]
+pub fn touch(destination: &Path, name: &str) {
+    // `create_new` would fail on an existing file; plain `create` on
+    // `File` truncates it. Neither is what `touch` should do, so open
+    // without truncating, creating only if it's missing.
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(destination.join(name))?;
+}
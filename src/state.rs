@@ -1,10 +1,16 @@
 use crate::*;
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
 use std::{
-    collections::HashMap,
-    ffi::OsString,
-    fs::{read_dir, DirEntry},
-    path::PathBuf,
+    collections::{HashMap, HashSet},
+    fs::{read_dir, DirEntry, File},
+    io::{BufRead, BufReader},
+    ops::Deref,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
 };
+use syntect::{highlighting::ThemeSet, parsing::SyntaxSet};
 use tui::{
     layout::Rect,
     text::{Span, Spans, Text},
@@ -21,10 +27,149 @@ pub fn entry_not_hidden(entry: &DirEntry) -> bool {
         .starts_with(".")
 }
 
+/// A directory entry flattened out of the expandable tree, along with
+/// enough of its ancestry to draw `tree`-style guide glyphs: `depth` is
+/// its indentation level, `last` is whether it's the last child of its
+/// parent, and `ancestors_last` is the same flag for each ancestor, so a
+/// continuing `│` is only drawn under ancestors that still have more
+/// siblings below.
+pub struct Node {
+    pub entry: DirEntry,
+    pub depth: usize,
+    pub last: bool,
+    pub ancestors_last: Vec<bool>,
+}
+
+impl Deref for Node {
+    type Target = DirEntry;
+
+    fn deref(&self) -> &DirEntry {
+        &self.entry
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum Mode {
     Normal,
     Command,
+    Search,
+}
+
+/// Case-insensitive subsequence fuzzy match. Returns a score (higher is
+/// better) and the matched character indices in `candidate`, or `None`
+/// when `query` isn't a subsequence of `candidate`.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    let candidate_chars = candidate.to_lowercase().chars().collect::<Vec<_>>();
+    let mut positions = Vec::with_capacity(query.len());
+    let mut score = 0;
+    let mut cursor = 0;
+    let mut previous = None;
+
+    for query_char in query.to_lowercase().chars() {
+        let index = cursor
+            + candidate_chars[cursor..]
+                .iter()
+                .position(|&character| character == query_char)?;
+
+        score += 1;
+        if index == 0 {
+            score += 10;
+        } else if matches!(candidate_chars[index - 1], '_' | '-' | '.' | '/') {
+            score += 8;
+        }
+        if previous == Some(index.wrapping_sub(1)) {
+            score += 5;
+        }
+
+        positions.push(index);
+        previous = Some(index);
+        cursor = index + 1;
+    }
+
+    Some((score, positions))
+}
+
+/// Reads `dir` non-recursively in the same order used throughout the
+/// listing: directories before files, hidden entries filtered unless
+/// `show_hidden`, then alphabetical.
+#[throws]
+fn sorted_entries(dir: &Path, show_hidden: bool) -> Vec<DirEntry> {
+    read_dir(dir)?
+        .flatten()
+        .filter(|entry| entry_not_hidden(entry) || show_hidden)
+        .sorted_unstable_by(|first, second| first.file_name().cmp(&second.file_name()))
+        .sorted_by_key(entry_not_hidden)
+        .sorted_by_key(|entry| !entry.path().is_dir())
+        .collect()
+}
+
+/// Flattens `entries` into depth-annotated `Node`s, recursing into any
+/// directory in `expanded`. A subdirectory that fails to read (e.g.
+/// permissions) is simply left without children rather than failing the
+/// whole listing.
+fn flatten(
+    entries: Vec<DirEntry>,
+    depth: usize,
+    ancestors_last: &[bool],
+    show_hidden: bool,
+    expanded: &HashSet<PathBuf>,
+) -> Vec<Node> {
+    let count = entries.len();
+    entries
+        .into_iter()
+        .enumerate()
+        .flat_map(|(index, entry)| {
+            let last = index + 1 == count;
+            let path = entry.path();
+            let is_dir = path.is_dir();
+            let mut nodes = vec![Node {
+                entry,
+                depth,
+                last,
+                ancestors_last: ancestors_last.to_vec(),
+            }];
+            if is_dir && expanded.contains(&path) {
+                let mut child_ancestors = ancestors_last.to_vec();
+                child_ancestors.push(last);
+                if let Ok(children) = sorted_entries(&path, show_hidden) {
+                    let children = flatten(children, depth + 1, &child_ancestors, show_hidden, expanded);
+                    nodes.extend(children);
+                }
+            }
+            nodes
+        })
+        .collect()
+}
+
+/// Renders a flat, non-recursive listing of `dir` for the preview pane.
+/// Built straight from `sorted_entries` instead of a full `Context`,
+/// since the preview is recomputed every frame and doesn't need marks,
+/// fuzzy state, or (crucially) a live `notify` watcher of its own.
+#[throws]
+fn preview_listing(dir: &Path, height: usize) -> Text {
+    let mut text = Text::default();
+    for (line, entry) in sorted_entries(dir, false)?.iter().take(height + 1).enumerate() {
+        if let Some(name) = entry.file_name().to_str() {
+            let is_dir = entry.path().is_dir();
+            let style = if is_dir {
+                style::directory(line == 0)
+            } else {
+                style::file(line == 0)
+            };
+            let mut spans = Spans::default();
+            spans.0.push(Span::raw(" "));
+            spans.0.push(Span::styled(name.to_string(), style));
+            if is_dir {
+                spans.0.push(Span::styled("/", style::reset()));
+            }
+            text.lines.push(spans);
+        }
+    }
+    text
 }
 
 impl Default for Mode {
@@ -33,12 +178,36 @@ impl Default for Mode {
     }
 }
 
-#[derive(Default)]
 pub struct Views {
     pub mode: Mode,
     pub index: usize,
     pub command: String,
     pub contexts: [Context; 4],
+    pub show_preview: bool,
+    pub syntax_set: Rc<SyntaxSet>,
+    pub theme_set: Rc<ThemeSet>,
+    pub bookmarks: HashMap<char, PathBuf>,
+    pub pending: Option<char>,
+    pub message: String,
+}
+
+impl Default for Views {
+    fn default() -> Self {
+        Self {
+            mode: Mode::default(),
+            index: usize::default(),
+            command: String::default(),
+            contexts: Default::default(),
+            show_preview: bool::default(),
+            // Loaded once and cached for the lifetime of the program so
+            // previews don't rebuild the syntax/theme tables every frame.
+            syntax_set: Rc::new(SyntaxSet::load_defaults_newlines()),
+            theme_set: Rc::new(ThemeSet::load_defaults()),
+            bookmarks: HashMap::new(),
+            pending: None,
+            message: String::new(),
+        }
+    }
 }
 
 impl Views {
@@ -49,9 +218,15 @@ impl Views {
             *context = Context::new()?;
         }
         views.contexts[0].read_directory()?;
+        views.show_preview = true;
+        views.bookmarks = bookmarks::load()?;
         views
     }
 
+    pub fn toggle_preview(&mut self) {
+        self.show_preview = !self.show_preview;
+    }
+
     pub fn current_context(&mut self) -> &mut Context {
         &mut self.contexts[self.index]
     }
@@ -66,6 +241,63 @@ impl Views {
         self.command = String::new();
         self.mode = Mode::Normal;
     }
+
+    /// Parses `self.command` (e.g. `copy`, `rename foo.txt`) and runs the
+    /// matching file operation against the active context. A failed
+    /// operation is reported through `self.message` rather than crashing
+    /// the program — a permission error, an `AlreadyExists` from `:mkdir`
+    /// or an `EXDEV` from `:move` shouldn't take down the whole TUI.
+    pub fn execute_command(&mut self) {
+        let command = self.command.clone();
+        let mut parts = command.split_whitespace();
+        let name = parts.next().unwrap_or_default();
+        let argument = parts.next();
+
+        if name == "bookmarks" {
+            let mut entries = self.bookmarks.iter().collect::<Vec<_>>();
+            entries.sort_by_key(|(letter, _)| **letter);
+            self.message = entries
+                .into_iter()
+                .map(|(letter, path)| format!("{} -> {}", letter, path.display()))
+                .collect::<Vec<_>>()
+                .join("  ");
+            return;
+        }
+
+        let context = self.current_context();
+        let destination = context.current_dir.clone();
+        let selected = context.selected();
+
+        let result = match name {
+            "copy" => operations::copy(&selected, &destination),
+            "move" => operations::r#move(&selected, &destination),
+            "delete" => operations::trash(&selected),
+            "rename" => match (selected.first(), argument) {
+                (Some(target), Some(name)) => operations::rename(target, name),
+                _ => return,
+            },
+            "mkdir" => match argument {
+                Some(name) => operations::mkdir(&destination, name),
+                _ => return,
+            },
+            "touch" => match argument {
+                Some(name) => operations::touch(&destination, name),
+                _ => return,
+            },
+            _ => return,
+        };
+
+        if let Err(error) = result {
+            self.message = error.to_string();
+        } else if matches!(name, "copy" | "move" | "delete") {
+            // Marks may span several directories (see `Context::selected`),
+            // so clear them everywhere rather than just the active buffer.
+            for buffer in context.buffers.values_mut() {
+                buffer.marked.clear();
+            }
+        }
+        if context.read_directory().is_err() {}
+    }
 }
 
 #[derive(Debug, Default)]
@@ -74,6 +306,7 @@ pub struct Buffer {
     pub scroll: usize,
     pub show_hidden: bool,
     pub marked: HashMap<PathBuf, bool>,
+    pub expanded: HashSet<PathBuf>,
 }
 
 impl Buffer {
@@ -89,8 +322,11 @@ pub struct Context {
     pub scroll: usize,
     pub terminal_size: Rect,
     pub current_dir: PathBuf,
-    pub directory: Vec<DirEntry>,
+    pub directory: Vec<Node>,
     pub buffers: HashMap<PathBuf, Buffer>,
+    pub watcher: Option<RecommendedWatcher>,
+    pub watch_rx: Option<Receiver<DebouncedEvent>>,
+    pub query: String,
 }
 
 impl Context {
@@ -101,6 +337,7 @@ impl Context {
             ..Self::default()
         };
         context.save_buffer();
+        context.watch()?;
         context
     }
 
@@ -162,7 +399,7 @@ impl Context {
         }
     }
 
-    pub fn target(&self) -> Option<&DirEntry> {
+    pub fn target(&self) -> Option<&Node> {
         match self.view() {
             Ok(iter) => iter.skip(self.cursor).next(),
             _ => None,
@@ -170,10 +407,10 @@ impl Context {
     }
 
     #[throws]
-    pub fn target_dir(&self) -> OsString {
+    pub fn target_dir(&self) -> PathBuf {
         let target = self.target();
         match target {
-            Some(target) if target.path().is_dir() => target.file_name(),
+            Some(target) if target.path().is_dir() => target.path(),
             _ => bail!("Error occured when trying to get current target"),
         }
     }
@@ -219,27 +456,107 @@ impl Context {
         }
     }
 
-    #[throws]
-    pub fn read(&self) -> impl Iterator<Item = DirEntry> {
-        read_dir(&self.current_dir)?
-            .flatten()
-            .filter(|entry| entry_not_hidden(entry) || self.show_hidden())
-            .sorted_unstable_by(|first, second| first.file_name().cmp(&second.file_name()))
-            .sorted_unstable_by_key(entry_not_hidden)
-            .sorted_unstable_by_key(|entry| !entry.path().is_dir())
+    /// The marked paths across every directory visited in this context, or
+    /// the cursor `target()` when nothing is marked anywhere. Marks are
+    /// kept per-directory in `buffers` so the `+` indicator only shows up
+    /// while browsing the directory they were made in, but they're
+    /// collected globally here so marking in one directory and navigating
+    /// to another before `:copy`/`:move` still pastes the right paths.
+    pub fn selected(&self) -> Vec<PathBuf> {
+        let marked = self
+            .buffers
+            .values()
+            .flat_map(|buffer| {
+                buffer
+                    .marked
+                    .iter()
+                    .filter(|(_, marked)| **marked)
+                    .map(|(path, _)| path.clone())
+            })
+            .collect::<Vec<_>>();
+        if marked.is_empty() {
+            self.target().map(|entry| entry.path()).into_iter().collect()
+        } else {
+            marked
+        }
     }
 
-    #[throws]
-    pub fn view(&self) -> impl Iterator<Item = &DirEntry> {
-        self.directory
+    /// Entries in `directory` narrowed to those fuzzy-matching `query`,
+    /// sorted by descending match score (stable on ties by name). Returns
+    /// everything, unsorted, when `query` is empty.
+    pub fn filtered(&self) -> Vec<&Node> {
+        if self.query.is_empty() {
+            return self.directory.iter().collect();
+        }
+
+        let mut matches = self
+            .directory
             .iter()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_str()?.to_string();
+                let (score, _) = fuzzy_match(&self.query, &name)?;
+                Some((score, entry))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by(|first, second| {
+            second
+                .0
+                .cmp(&first.0)
+                .then_with(|| first.1.file_name().cmp(&second.1.file_name()))
+        });
+        matches.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    #[throws]
+    pub fn view(&self) -> impl Iterator<Item = &Node> {
+        self.filtered()
+            .into_iter()
             .skip(self.scroll)
             .take(self.height() + 1)
     }
 
     #[throws]
     pub fn read_directory(&mut self) {
-        self.directory = self.read()?.collect();
+        let show_hidden = self.show_hidden();
+        let expanded = self
+            .buffers
+            .get(&self.current_dir)
+            .map(|buffer| buffer.expanded.clone())
+            .unwrap_or_default();
+        let entries = sorted_entries(&self.current_dir, show_hidden)?;
+        self.directory = flatten(entries, 0, &[], show_hidden, &expanded);
+    }
+
+    /// Starts watching `current_dir` for changes, replacing any previous
+    /// watcher so navigation always follows the active directory.
+    #[throws]
+    pub fn watch(&mut self) {
+        let (sender, receiver) = channel();
+        let mut watcher = watcher(sender, Duration::from_millis(200))?;
+        watcher.watch(&self.current_dir, RecursiveMode::NonRecursive)?;
+        self.watcher = Some(watcher);
+        self.watch_rx = Some(receiver);
+    }
+
+    /// Drains pending filesystem events and re-reads the directory if one
+    /// of them touched it. Returns whether a re-read happened. If
+    /// `current_dir` itself was removed from under us, the re-read fails
+    /// and is ignored rather than propagated — the listing simply goes
+    /// stale until the user navigates away, instead of the app crashing.
+    pub fn process_watch_events(&mut self) -> bool {
+        let mut changed = false;
+        if let Some(receiver) = &self.watch_rx {
+            while let Ok(event) = receiver.try_recv() {
+                match event {
+                    DebouncedEvent::Create(_)
+                    | DebouncedEvent::Remove(_)
+                    | DebouncedEvent::Rename(_, _) => changed = true,
+                    _ => {}
+                }
+            }
+        }
+        if changed && self.read_directory().is_err() {}
+        changed
     }
 
     #[throws]
@@ -257,15 +574,80 @@ impl Context {
                 } else {
                     items.push(Span::raw(" "));
                 }
+                if entry.depth > 0 && self.query.is_empty() {
+                    for last in &entry.ancestors_last {
+                        let guide = if *last { "  " } else { "│ " };
+                        items.push(Span::styled(guide, style::dim()));
+                    }
+                    let branch = if entry.last { "└─" } else { "├─" };
+                    items.push(Span::styled(branch, style::dim()));
+                }
+                let base = if is_dir {
+                    style::directory(highlight)
+                } else {
+                    style::file(highlight)
+                };
+                let matched = if self.query.is_empty() {
+                    Vec::new()
+                } else {
+                    fuzzy_match(&self.query, &input)
+                        .map(|(_, positions)| positions)
+                        .unwrap_or_default()
+                };
+                for (index, character) in input.chars().enumerate() {
+                    let style = if matched.contains(&index) {
+                        style::matched()
+                    } else {
+                        base
+                    };
+                    items.push(Span::styled(character.to_string(), style));
+                }
                 if is_dir {
-                    items.push(Span::styled(input, style::directory(highlight)));
                     items.push(Span::styled("/", style::reset()));
-                } else {
-                    items.push(Span::styled(input, style::file(highlight)));
                 }
                 text.lines.push(spans);
             }
         }
         text
     }
+
+    /// Renders a preview of the highlighted target: a directory listing for
+    /// directories, or a syntax-highlighted slice of the first `height()`
+    /// lines for regular files. Renders empty `Text` instead of failing —
+    /// the preview re-runs every frame for whatever the cursor happens to
+    /// be sitting on, including unreadable directories (EACCES), broken
+    /// symlinks, or non-regular files like device nodes and FIFOs (which
+    /// `File::open` can block on), so it must never crash or hang.
+    pub fn preview(&self, syntax_set: &SyntaxSet, theme_set: &ThemeSet) -> Text {
+        match self.target() {
+            Some(entry) => {
+                let path = entry.path();
+                if path.is_dir() {
+                    preview_listing(&path, self.height()).unwrap_or_default()
+                } else {
+                    match path.metadata() {
+                        Ok(metadata) if metadata.is_file() => match File::open(&path) {
+                            Ok(file) => {
+                                let reader = BufReader::new(file);
+                                let lines = reader
+                                    .lines()
+                                    .take(self.height())
+                                    .flatten()
+                                    .collect::<Vec<_>>()
+                                    .join("\n");
+                                let extension =
+                                    path.extension().and_then(|extension| extension.to_str());
+                                Text {
+                                    lines: style::highlight(&lines, extension, syntax_set, theme_set),
+                                }
+                            }
+                            Err(_) => Text::default(),
+                        },
+                        _ => Text::default(),
+                    }
+                }
+            }
+            None => Text::default(),
+        }
+    }
 }
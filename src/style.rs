@@ -1,4 +1,13 @@
-use tui::style::{Color, Modifier, Style};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Style as SyntectStyle, ThemeSet},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+};
 
 pub fn file(highlight: bool) -> Style {
     match highlight {
@@ -18,3 +27,51 @@ pub fn directory(highlight: bool) -> Style {
 pub fn reset() -> Style {
     Style::default().fg(Color::Reset)
 }
+
+pub fn dim() -> Style {
+    Style::default().add_modifier(Modifier::DIM)
+}
+
+pub fn matched() -> Style {
+    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+}
+
+/// Syntax-highlights `content` line by line, falling back to plain spans
+/// when the extension isn't recognised.
+pub fn highlight(
+    content: &str,
+    extension: Option<&str>,
+    syntax_set: &SyntaxSet,
+    theme_set: &ThemeSet,
+) -> Vec<Spans<'static>> {
+    let syntax = extension.and_then(|extension| syntax_set.find_syntax_by_extension(extension));
+    match syntax {
+        Some(syntax) => {
+            let theme = &theme_set.themes["base16-ocean.dark"];
+            let mut highlighter = HighlightLines::new(syntax, theme);
+            LinesWithEndings::from(content)
+                .filter_map(|line| highlighter.highlight_line(line, syntax_set).ok())
+                .map(|ranges| {
+                    Spans::from(
+                        ranges
+                            .into_iter()
+                            .map(|(style, text)| syntect_span(style, text))
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .collect()
+        }
+        None => content
+            .lines()
+            .map(|line| Spans::from(Span::raw(line.to_string())))
+            .collect(),
+    }
+}
+
+fn syntect_span(style: SyntectStyle, text: &str) -> Span<'static> {
+    let color = style.foreground;
+    Span::styled(
+        text.trim_end_matches('\n').to_string(),
+        Style::default().fg(Color::Rgb(color.r, color.g, color.b)),
+    )
+}